@@ -0,0 +1,203 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use desc::{Desc, Describer};
+use errors::Result;
+use metrics::{Collector, Exemplar, Metric, Opts};
+use proto;
+
+#[derive(Debug)]
+struct CounterCore {
+    desc: Desc,
+    label_pairs: Vec<proto::LabelPair>,
+    val_bits: AtomicU64,
+    // Holds only the rarely-written exemplar, swapped via an atomic
+    // pointer rather than a mutex: a concurrent scrape reading the
+    // exemplar never blocks (or is blocked by) a hot-path `inc`/`inc_by`.
+    exemplar: ArcSwapOption<Exemplar>,
+}
+
+/// A [`Metric`](::Metric) that represents a single numerical value that
+/// only ever goes up.
+#[derive(Clone, Debug)]
+pub struct Counter {
+    core: Arc<CounterCore>,
+}
+
+impl Counter {
+    /// Create a `Counter` with the given name and help text.
+    pub fn new<S1: Into<String>, S2: Into<String>>(name: S1, help: S2) -> Result<Counter> {
+        Counter::with_opts(Opts::new(name, help))
+    }
+
+    /// Create a `Counter` from `Opts`.
+    pub fn with_opts(opts: Opts<[&'static str; 0]>) -> Result<Counter> {
+        let desc = opts.describe()?;
+        let label_pairs = desc.const_label_pairs.clone();
+        Ok(Counter {
+            core: Arc::new(CounterCore {
+                desc,
+                label_pairs,
+                val_bits: AtomicU64::new(0f64.to_bits()),
+                exemplar: ArcSwapOption::from(None),
+            }),
+        })
+    }
+
+    /// Increment the counter by 1.
+    pub fn inc(&self) {
+        self.inc_by(1.0);
+    }
+
+    /// Increment the counter by `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` < 0.
+    pub fn inc_by(&self, v: f64) {
+        assert!(v >= 0.0, "counter cannot be decreased");
+        loop {
+            let bits = self.core.val_bits.load(Ordering::Acquire);
+            let new = f64::from_bits(bits) + v;
+            if self
+                .core
+                .val_bits
+                .compare_exchange_weak(bits, new.to_bits(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Return the counter's current value.
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.core.val_bits.load(Ordering::Acquire))
+    }
+
+    /// Return the most recently recorded exemplar, if any.
+    pub fn exemplar(&self) -> Option<Exemplar> {
+        self.core.exemplar.load_full().map(|e| (*e).clone())
+    }
+
+    pub(crate) fn set_exemplar(&self, exemplar: Exemplar) {
+        self.core.exemplar.store(Some(Arc::new(exemplar)));
+    }
+}
+
+impl Metric for Counter {
+    fn metric(&self) -> proto::Metric {
+        let mut c = proto::Counter::default();
+        c.set_value(self.get());
+
+        let mut m = proto::Metric::default();
+        m.set_label(self.core.label_pairs.clone());
+        m.set_counter(c);
+        m
+    }
+}
+
+impl Collector for Counter {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.core.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut mf = proto::MetricFamily::default();
+        mf.set_name(self.core.desc.fq_name.clone());
+        mf.set_help(self.core.desc.help.clone());
+        mf.set_metric(vec![self.metric()]);
+        vec![mf]
+    }
+}
+
+#[derive(Debug)]
+struct IntCounterCore {
+    desc: Desc,
+    label_pairs: Vec<proto::LabelPair>,
+    val: AtomicU64,
+}
+
+/// Like [`Counter`](self::Counter), but for integer values, avoiding the
+/// CAS loop a floating-point atomic add requires.
+#[derive(Clone, Debug)]
+pub struct IntCounter {
+    core: Arc<IntCounterCore>,
+}
+
+impl IntCounter {
+    /// Create an `IntCounter` with the given name and help text.
+    pub fn new<S1: Into<String>, S2: Into<String>>(name: S1, help: S2) -> Result<IntCounter> {
+        IntCounter::with_opts(Opts::new(name, help))
+    }
+
+    /// Create an `IntCounter` from `Opts`.
+    pub fn with_opts(opts: Opts<[&'static str; 0]>) -> Result<IntCounter> {
+        let desc = opts.describe()?;
+        let label_pairs = desc.const_label_pairs.clone();
+        Ok(IntCounter {
+            core: Arc::new(IntCounterCore {
+                desc,
+                label_pairs,
+                val: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Increment the counter by 1.
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Increment the counter by `v`.
+    pub fn inc_by(&self, v: u64) {
+        self.core.val.fetch_add(v, Ordering::Relaxed);
+    }
+
+    /// Return the counter's current value.
+    pub fn get(&self) -> u64 {
+        self.core.val.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for IntCounter {
+    fn metric(&self) -> proto::Metric {
+        let mut c = proto::Counter::default();
+        c.set_value(self.get() as f64);
+
+        let mut m = proto::Metric::default();
+        m.set_label(self.core.label_pairs.clone());
+        m.set_counter(c);
+        m
+    }
+}
+
+impl Collector for IntCounter {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.core.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut mf = proto::MetricFamily::default();
+        mf.set_name(self.core.desc.fq_name.clone());
+        mf.set_help(self.core.desc.help.clone());
+        mf.set_metric(vec![self.metric()]);
+        vec![mf]
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use errors::Result;
+use proto::LabelPair;
+
+/// Implemented by anything that can build a [`Desc`](::Desc), most notably
+/// [`Opts`](::Opts).
+pub trait Describer {
+    /// Build the [`Desc`](::Desc) for this describer.
+    fn describe(&self) -> Result<Desc>;
+}
+
+/// `Desc` is the descriptor used by every [`Metric`](::Metric). A
+/// [`Registry`](::Registry) uses `id` to reject a duplicate registration and
+/// `dim_hash` to reject a metric whose fully-qualified name is already
+/// registered with different variable label names. Const label names are
+/// not part of the `dim_hash` check.
+#[derive(Debug, Clone)]
+pub struct Desc {
+    /// The metric's fully-qualified name.
+    pub fq_name: String,
+    /// The metric's help text.
+    pub help: String,
+    /// The metric's const labels, sorted by name.
+    pub const_label_pairs: Vec<LabelPair>,
+    /// The names of the metric's variable labels.
+    pub variable_labels: Vec<String>,
+    /// A hash identifying this exact `Desc` (name + const labels).
+    pub id: u64,
+    /// A hash identifying this `Desc`'s dimensionality (its variable label
+    /// names), used to catch a metric re-registered with a different set
+    /// of variable labels under the same name.
+    pub dim_hash: u64,
+}
+
+impl Desc {
+    /// Build a new `Desc` from its components, sorting `const_labels` into
+    /// `const_label_pairs` and deriving `id`/`dim_hash`.
+    pub fn new(
+        fq_name: String,
+        help: String,
+        variable_labels: Vec<String>,
+        const_labels: HashMap<String, String>,
+    ) -> Result<Desc> {
+        let mut const_label_pairs: Vec<LabelPair> = const_labels
+            .into_iter()
+            .map(|(name, value)| {
+                let mut label_pair = LabelPair::new();
+                label_pair.set_name(name);
+                label_pair.set_value(value);
+                label_pair
+            })
+            .collect();
+        const_label_pairs.sort();
+
+        let mut hasher = DefaultHasher::new();
+        fq_name.hash(&mut hasher);
+        for label_pair in &const_label_pairs {
+            label_pair.get_name().hash(&mut hasher);
+            label_pair.get_value().hash(&mut hasher);
+        }
+        let id = hasher.finish();
+
+        let mut dim_hasher = DefaultHasher::new();
+        for label_name in &variable_labels {
+            label_name.hash(&mut dim_hasher);
+        }
+        let dim_hash = dim_hasher.finish();
+
+        Ok(Desc {
+            fq_name,
+            help,
+            const_label_pairs,
+            variable_labels,
+            id,
+            dim_hash,
+        })
+    }
+}
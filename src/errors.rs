@@ -0,0 +1,54 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error;
+use std::fmt;
+use std::result;
+
+/// The error type for this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A collector whose descriptors collide with one already registered
+    /// was registered again.
+    AlreadyReg,
+    /// The number of label values passed to a metric did not match the
+    /// number of variable labels in its `Desc`.
+    InconsistentCardinality {
+        /// Number of variable labels the `Desc` expects.
+        expect: usize,
+        /// Number of label values actually supplied.
+        got: usize,
+    },
+    /// A catch-all for errors that do not warrant their own variant.
+    Msg(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::AlreadyReg => write!(f, "duplicate metrics collector registration attempted"),
+            Error::InconsistentCardinality { expect, got } => write!(
+                f,
+                "inconsistent label cardinality: expect {} label values, got {}",
+                expect, got
+            ),
+            Error::Msg(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// A specialized [`Result`](std::result::Result) type for this crate.
+pub type Result<T> = result::Result<T, Error>;
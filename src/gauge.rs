@@ -0,0 +1,117 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use desc::{Desc, Describer};
+use errors::Result;
+use metrics::{Collector, Metric, Opts};
+use proto;
+
+#[derive(Debug)]
+struct GaugeCore {
+    desc: Desc,
+    label_pairs: Vec<proto::LabelPair>,
+    val_bits: AtomicU64,
+}
+
+/// A [`Metric`](::Metric) that represents a single numerical value that can
+/// arbitrarily go up or down.
+#[derive(Clone, Debug)]
+pub struct Gauge {
+    core: Arc<GaugeCore>,
+}
+
+impl Gauge {
+    /// Create a `Gauge` with the given name and help text.
+    pub fn new<S1: Into<String>, S2: Into<String>>(name: S1, help: S2) -> Result<Gauge> {
+        Gauge::with_opts(Opts::new(name, help))
+    }
+
+    /// Create a `Gauge` from `Opts`.
+    pub fn with_opts(opts: Opts<[&'static str; 0]>) -> Result<Gauge> {
+        let desc = opts.describe()?;
+        let label_pairs = desc.const_label_pairs.clone();
+        Ok(Gauge {
+            core: Arc::new(GaugeCore {
+                desc,
+                label_pairs,
+                val_bits: AtomicU64::new(0f64.to_bits()),
+            }),
+        })
+    }
+
+    /// Set the gauge to `v`.
+    pub fn set(&self, v: f64) {
+        self.core.val_bits.store(v.to_bits(), Ordering::Release);
+    }
+
+    /// Increment the gauge by 1.
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    /// Decrement the gauge by 1.
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    /// Add `v` to the gauge (use a negative value to subtract).
+    pub fn add(&self, v: f64) {
+        loop {
+            let bits = self.core.val_bits.load(Ordering::Acquire);
+            let new = f64::from_bits(bits) + v;
+            if self
+                .core
+                .val_bits
+                .compare_exchange_weak(bits, new.to_bits(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Return the gauge's current value.
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.core.val_bits.load(Ordering::Acquire))
+    }
+}
+
+impl Metric for Gauge {
+    fn metric(&self) -> proto::Metric {
+        let mut g = proto::Gauge::default();
+        g.set_value(self.get());
+
+        let mut m = proto::Metric::default();
+        m.set_label(self.core.label_pairs.clone());
+        m.set_gauge(g);
+        m
+    }
+}
+
+impl Collector for Gauge {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.core.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut mf = proto::MetricFamily::default();
+        mf.set_name(self.core.desc.fq_name.clone());
+        mf.set_help(self.core.desc.help.clone());
+        mf.set_metric(vec![self.metric()]);
+        vec![mf]
+    }
+}
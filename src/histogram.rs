@@ -0,0 +1,187 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use desc::{Desc, Describer};
+use errors::Result;
+use metrics::{Collector, Exemplar, Metric, Opts};
+use proto;
+
+const DEFAULT_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug)]
+struct HistogramCore {
+    desc: Desc,
+    label_pairs: Vec<proto::LabelPair>,
+    upper_bounds: Vec<f64>,
+    // Each bucket count is already cumulative: `observe`/`flush_local`
+    // increment every bucket whose upper bound is >= the observed value.
+    bucket_counts: Vec<AtomicU64>,
+    // Swapped via an atomic pointer, not a mutex, so a concurrent scrape
+    // reading a bucket's exemplar never blocks (or is blocked by) a
+    // hot-path `observe`.
+    bucket_exemplars: Vec<ArcSwapOption<Exemplar>>,
+    sum_bits: AtomicU64,
+    count: AtomicU64,
+}
+
+/// A [`Metric`](::Metric) that samples observations into configurable
+/// buckets and exposes their cumulative counts, plus a running sum and
+/// count.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    core: Arc<HistogramCore>,
+}
+
+impl Histogram {
+    /// Create a `Histogram` with the given name, help text, and the
+    /// library's default bucket boundaries.
+    pub fn new<S1: Into<String>, S2: Into<String>>(name: S1, help: S2) -> Result<Histogram> {
+        Histogram::with_opts(Opts::new(name, help), DEFAULT_BUCKETS.to_vec())
+    }
+
+    /// Create a `Histogram` from `Opts` and explicit bucket boundaries.
+    pub fn with_opts(opts: Opts<[&'static str; 0]>, mut buckets: Vec<f64>) -> Result<Histogram> {
+        let desc = opts.describe()?;
+        let label_pairs = desc.const_label_pairs.clone();
+
+        buckets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        buckets.dedup();
+        let n = buckets.len();
+
+        Ok(Histogram {
+            core: Arc::new(HistogramCore {
+                desc,
+                label_pairs,
+                upper_bounds: buckets,
+                bucket_counts: (0..n).map(|_| AtomicU64::new(0)).collect(),
+                bucket_exemplars: (0..n).map(|_| ArcSwapOption::from(None)).collect(),
+                sum_bits: AtomicU64::new(0f64.to_bits()),
+                count: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// The histogram's sorted bucket upper bounds.
+    pub fn upper_bounds(&self) -> &[f64] {
+        &self.core.upper_bounds
+    }
+
+    /// Add a single observation.
+    pub fn observe(&self, v: f64) {
+        self.add_sum(v);
+        self.core.count.fetch_add(1, Ordering::Relaxed);
+        for (i, upper_bound) in self.core.upper_bounds.iter().enumerate() {
+            if v <= *upper_bound {
+                self.core.bucket_counts[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn add_sum(&self, v: f64) {
+        loop {
+            let bits = self.core.sum_bits.load(Ordering::Acquire);
+            let new = f64::from_bits(bits) + v;
+            if self
+                .core
+                .sum_bits
+                .compare_exchange_weak(bits, new.to_bits(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Fold a locally-buffered `sum`, observation `count`, and per-bucket
+    /// `counts` (indexed the same as
+    /// [`upper_bounds`](Histogram::upper_bounds)) into this histogram's
+    /// shared atomics in one pass, using the same atomic adds `observe`
+    /// uses so correctness holds under concurrent flushes.
+    pub(crate) fn flush_local(&self, sum: f64, count: u64, counts: &[u64]) {
+        self.add_sum(sum);
+        self.core.count.fetch_add(count, Ordering::Relaxed);
+        for (i, c) in counts.iter().enumerate() {
+            if *c != 0 {
+                self.core.bucket_counts[i].fetch_add(*c, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The most recently recorded exemplar for the bucket with the given
+    /// upper bound, if any.
+    pub fn bucket_exemplar(&self, upper_bound: f64) -> Option<Exemplar> {
+        let i = self
+            .core
+            .upper_bounds
+            .iter()
+            .position(|b| *b == upper_bound)?;
+        self.core.bucket_exemplars[i].load_full().map(|e| (*e).clone())
+    }
+
+    pub(crate) fn set_bucket_exemplar(&self, v: f64, exemplar: Exemplar) {
+        for (i, upper_bound) in self.core.upper_bounds.iter().enumerate() {
+            if v <= *upper_bound {
+                self.core.bucket_exemplars[i].store(Some(Arc::new(exemplar)));
+                return;
+            }
+        }
+    }
+
+    fn proto_buckets(&self) -> Vec<proto::Bucket> {
+        self.core
+            .upper_bounds
+            .iter()
+            .zip(self.core.bucket_counts.iter())
+            .map(|(upper_bound, count)| {
+                let mut b = proto::Bucket::default();
+                b.set_upper_bound(*upper_bound);
+                b.set_cumulative_count(count.load(Ordering::Relaxed));
+                b
+            })
+            .collect()
+    }
+}
+
+impl Metric for Histogram {
+    fn metric(&self) -> proto::Metric {
+        let mut h = proto::Histogram::default();
+        h.set_sample_sum(f64::from_bits(self.core.sum_bits.load(Ordering::Acquire)));
+        h.set_sample_count(self.core.count.load(Ordering::Relaxed));
+        h.set_bucket(self.proto_buckets());
+
+        let mut m = proto::Metric::default();
+        m.set_label(self.core.label_pairs.clone());
+        m.set_histogram(h);
+        m
+    }
+}
+
+impl Collector for Histogram {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.core.desc]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut mf = proto::MetricFamily::default();
+        mf.set_name(self.core.desc.fq_name.clone());
+        mf.set_help(self.core.desc.help.clone());
+        mf.set_metric(vec![self.metric()]);
+        vec![mf]
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::OnceLock;
+
+extern crate arc_swap;
+
+pub mod counter;
+pub mod desc;
+pub mod errors;
+pub mod gauge;
+pub mod histogram;
+pub mod metrics;
+pub mod proto;
+pub mod registry;
+
+pub use counter::{Counter, IntCounter};
+pub use desc::{Desc, Describer};
+pub use errors::{Error, Result};
+pub use gauge::Gauge;
+pub use histogram::Histogram;
+pub use metrics::*;
+pub use registry::Registry;
+
+fn default_registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::new)
+}
+
+/// Register a collector with the default registry.
+pub fn try_register(c: Box<dyn Collector>) -> Result<()> {
+    default_registry().try_register(c)
+}
+
+/// Unregister a collector from the default registry.
+pub fn try_unregister(c: Box<dyn Collector>) -> Result<()> {
+    default_registry().try_unregister(c)
+}
+
+/// Gather metrics from the default registry.
+pub fn gather() -> Vec<proto::MetricFamily> {
+    default_registry().gather()
+}
@@ -12,13 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::cmp::{Eq, Ord, Ordering, PartialOrd};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::slice::Iter;
+use std::sync::OnceLock;
+use std::time::Instant;
 
+use counter::{Counter, IntCounter};
 use desc::{Desc, Describer};
-use errors::Result;
+use errors::{Error, Result};
+use gauge::Gauge;
+use histogram::Histogram;
 use proto::{self, LabelPair};
 
 pub const SEPARATOR_BYTE: u8 = 0xFF;
@@ -31,7 +37,7 @@ pub trait Labels: Debug + Clone + Send + Sync {
 
     fn to_owned(&self) -> Self::Owned;
 
-    fn iter(&self) -> Iter<Self::Item> {
+    fn iter(&self) -> Iter<'_, Self::Item> {
         self.as_slice().iter()
     }
 }
@@ -205,6 +211,517 @@ pub trait Metric: Sync + Send + Clone {
     fn metric(&self) -> proto::Metric;
 }
 
+/// An output format's view of a single metric sample. Implemented once per
+/// wire format (Prometheus text, protobuf, OpenMetrics, ...) and driven by
+/// [`EncodeMetric`](self::EncodeMetric) implementations, so that adding a
+/// format does not require touching every metric type.
+pub trait MetricEncoder {
+    /// Encode a counter's value.
+    fn encode_counter(&mut self, value: f64) -> Result<()>;
+
+    /// Encode a gauge's value.
+    fn encode_gauge(&mut self, value: f64) -> Result<()>;
+
+    /// Encode a histogram's cumulative `buckets` (upper bound, cumulative
+    /// count), `sum`, and total `count`.
+    fn encode_histogram(&mut self, buckets: &[(f64, u64)], sum: f64, count: u64) -> Result<()>;
+
+    /// Encode the label pairs shared by whichever sample is encoded next.
+    fn encode_labels(&mut self, labels: &[LabelPair]) -> Result<()>;
+
+    /// Encode an exemplar of the sample just encoded. A counter calls this
+    /// at most once, after `encode_counter`; a histogram calls it after
+    /// `encode_histogram` once for each bucket that holds an exemplar, in
+    /// bucket order.
+    ///
+    /// The default implementation ignores the exemplar, so an encoder that
+    /// does not override it drops exemplars from its output. This crate
+    /// ships no encoder that overrides it.
+    fn encode_exemplar(&mut self, _exemplar: &Exemplar) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The OpenMetrics 128-rune limit on the combined size of a sample's own
+/// labels plus its exemplar's labels.
+const EXEMPLAR_MAX_RUNES: usize = 128;
+
+/// A single exemplar attached to a counter increment or to the histogram
+/// bucket an observation fell into: the label set of the originating
+/// event (e.g. a trace ID), the observed value, and an optional
+/// timestamp. At most one exemplar is kept per counter and per histogram
+/// bucket — the most recent observation wins.
+#[derive(Debug, Clone)]
+pub struct Exemplar {
+    label_pairs: Vec<LabelPair>,
+    value: f64,
+    timestamp_ms: Option<u64>,
+}
+
+impl Exemplar {
+    fn new(labels: HashMap<String, String>, value: f64, metric_labels: &[LabelPair]) -> Result<Exemplar> {
+        let label_pairs = common_label_pairs(&labels);
+
+        let rune_count: usize = label_pairs
+            .iter()
+            .chain(metric_labels.iter())
+            .map(|l| l.get_name().chars().count() + l.get_value().chars().count())
+            .sum();
+        if rune_count > EXEMPLAR_MAX_RUNES {
+            return Err(Error::Msg(format!(
+                "exemplar labels together with the metric's own labels take up {} runes, \
+                 the OpenMetrics limit is {}",
+                rune_count, EXEMPLAR_MAX_RUNES
+            )));
+        }
+
+        Ok(Exemplar {
+            label_pairs,
+            value,
+            timestamp_ms: Some(unix_millis()),
+        })
+    }
+
+    /// The exemplar's label pairs.
+    pub fn label_pairs(&self) -> &[LabelPair] {
+        &self.label_pairs
+    }
+
+    /// The value that was observed when this exemplar was recorded.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Milliseconds since the Unix epoch at which this exemplar was
+    /// recorded, if known.
+    pub fn timestamp_ms(&self) -> Option<u64> {
+        self.timestamp_ms
+    }
+}
+
+fn unix_millis() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// A metric type that knows how to drive a [`MetricEncoder`](self::MetricEncoder)
+/// with its own kind and value, without the encoder needing to know about
+/// the metric type itself. Object-safe so a [`Registry`](::Registry) (or
+/// any other caller) can hold `Box<dyn EncodeMetric>` and iterate uniformly
+/// over heterogeneous metrics.
+pub trait EncodeMetric {
+    /// Drive `encoder` with this metric's labels and value.
+    fn encode(&self, encoder: &mut dyn MetricEncoder) -> Result<()>;
+}
+
+fn histogram_buckets(h: &proto::Histogram) -> Vec<(f64, u64)> {
+    h.get_bucket()
+        .iter()
+        .map(|b| (b.get_upper_bound(), b.get_cumulative_count()))
+        .collect()
+}
+
+impl EncodeMetric for Counter {
+    fn encode(&self, encoder: &mut dyn MetricEncoder) -> Result<()> {
+        let m = self.metric();
+        encoder.encode_labels(m.get_label())?;
+        encoder.encode_counter(m.get_counter().get_value())?;
+        if let Some(exemplar) = self.exemplar() {
+            encoder.encode_exemplar(&exemplar)?;
+        }
+        Ok(())
+    }
+}
+
+impl EncodeMetric for Gauge {
+    fn encode(&self, encoder: &mut dyn MetricEncoder) -> Result<()> {
+        let m = self.metric();
+        encoder.encode_labels(m.get_label())?;
+        encoder.encode_gauge(m.get_gauge().get_value())
+    }
+}
+
+impl EncodeMetric for Histogram {
+    fn encode(&self, encoder: &mut dyn MetricEncoder) -> Result<()> {
+        let m = self.metric();
+        encoder.encode_labels(m.get_label())?;
+        let h = m.get_histogram();
+        let buckets = histogram_buckets(h);
+        encoder.encode_histogram(&buckets, h.get_sample_sum(), h.get_sample_count())?;
+        for (upper_bound, _) in &buckets {
+            if let Some(exemplar) = self.bucket_exemplar(*upper_bound) {
+                encoder.encode_exemplar(&exemplar)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Counter {
+    /// Increment the counter by 1, attaching `labels` as an exemplar for
+    /// this increment (e.g. the trace ID of the request being counted).
+    /// Only the most recent exemplar is kept; it is handed to
+    /// [`MetricEncoder::encode_exemplar`](self::MetricEncoder::encode_exemplar)
+    /// by [`EncodeMetric::encode`](self::EncodeMetric::encode) and is not
+    /// part of [`Registry::gather`](::Registry::gather) output. Writing the
+    /// exemplar is lock-free, so it never blocks a concurrent scrape.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `labels` combined with the counter's own
+    /// labels would exceed the OpenMetrics 128-rune limit.
+    pub fn inc_with_exemplar(&self, labels: HashMap<String, String>) -> Result<()> {
+        // Build (and validate) the exemplar before mutating the counter,
+        // so a rejected exemplar never leaves a silently-recorded
+        // increment behind.
+        let exemplar = Exemplar::new(labels, 1.0, self.metric().get_label())?;
+        self.inc();
+        self.set_exemplar(exemplar);
+        Ok(())
+    }
+}
+
+impl Histogram {
+    /// Observe `v`, attaching `labels` as an exemplar for the bucket `v`
+    /// falls into (e.g. the trace ID of the request being measured). Each
+    /// bucket keeps only its most recent exemplar; exemplars are handed to
+    /// [`MetricEncoder::encode_exemplar`](self::MetricEncoder::encode_exemplar)
+    /// by [`EncodeMetric::encode`](self::EncodeMetric::encode) and are not
+    /// part of [`Registry::gather`](::Registry::gather) output. Writing the
+    /// exemplar is lock-free, so it never blocks a concurrent scrape.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `labels` combined with the histogram's own
+    /// labels would exceed the OpenMetrics 128-rune limit.
+    pub fn observe_with_exemplar(&self, v: f64, labels: HashMap<String, String>) -> Result<()> {
+        // Build (and validate) the exemplar before mutating the
+        // histogram, so a rejected exemplar never leaves a
+        // silently-recorded observation behind.
+        let exemplar = Exemplar::new(labels, v, self.metric().get_label())?;
+        self.observe(v);
+        self.set_bucket_exemplar(v, exemplar);
+        Ok(())
+    }
+}
+
+/// A metric that buffers updates in plain, non-atomic storage (typically
+/// thread-local) instead of touching a shared [`Metric`](self::Metric) on
+/// every call, and folds the buffered delta into the shared metric only
+/// occasionally.
+///
+/// Implementors are cheap to update from a single thread but are not
+/// `Sync`; they are meant to live behind a `thread_local!` next to the
+/// shared metric they buffer for.
+pub trait LocalMetric {
+    /// Flush the locally buffered value into the parent metric, resetting
+    /// the local buffer to its zero value.
+    fn flush(&self);
+}
+
+/// A [`LocalMetric`](self::LocalMetric) that only flushes itself once
+/// `flush_interval_millis` milliseconds have elapsed since `last_flush`.
+pub trait MayFlush: LocalMetric {
+    /// Flush if enough time has passed since `last_flush`, updating
+    /// `last_flush` to the current time when it does.
+    fn try_flush(&self, last_flush: &Cell<u64>, flush_interval_millis: u64) {
+        let now = monotonic_millis();
+        if now.saturating_sub(last_flush.get()) >= flush_interval_millis {
+            self.flush();
+            last_flush.set(now);
+        }
+    }
+}
+
+/// Milliseconds elapsed since an arbitrary, process-wide fixed point. Only
+/// useful for measuring intervals (as [`MayFlush::try_flush`] does), never
+/// for interpreting as wall-clock time.
+fn monotonic_millis() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64
+}
+
+/// A thread-local accumulator for a [`Counter`](::Counter). Increments are
+/// buffered in a plain [`Cell`](std::cell::Cell) and only periodically
+/// folded into the shared counter via [`flush`](LocalMetric::flush),
+/// avoiding atomic contention on hot paths.
+///
+/// Deliberately not `Clone`: it is the sole owner of a buffered delta, and
+/// a clone would flush that same delta into the shared counter twice once
+/// both copies drop.
+pub struct LocalCounter {
+    counter: Counter,
+    val: Cell<f64>,
+}
+
+impl LocalCounter {
+    fn new(counter: Counter) -> LocalCounter {
+        LocalCounter {
+            counter,
+            val: Cell::new(0.0),
+        }
+    }
+
+    /// Increment the local buffer by 1.
+    pub fn inc(&self) {
+        self.inc_by(1.0);
+    }
+
+    /// Increment the local buffer by `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` < 0.
+    pub fn inc_by(&self, v: f64) {
+        assert!(v >= 0.0, "counter cannot be decreased");
+        self.val.set(self.val.get() + v);
+    }
+}
+
+impl LocalMetric for LocalCounter {
+    fn flush(&self) {
+        let v = self.val.replace(0.0);
+        if v != 0.0 {
+            self.counter.inc_by(v);
+        }
+    }
+}
+
+impl MayFlush for LocalCounter {}
+
+impl Drop for LocalCounter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A thread-local accumulator for an [`IntCounter`](::IntCounter). See
+/// [`LocalCounter`](self::LocalCounter) for why this is not `Clone`.
+pub struct LocalIntCounter {
+    counter: IntCounter,
+    val: Cell<u64>,
+}
+
+impl LocalIntCounter {
+    fn new(counter: IntCounter) -> LocalIntCounter {
+        LocalIntCounter {
+            counter,
+            val: Cell::new(0),
+        }
+    }
+
+    /// Increment the local buffer by 1.
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Increment the local buffer by `v`.
+    pub fn inc_by(&self, v: u64) {
+        self.val.set(self.val.get() + v);
+    }
+}
+
+impl LocalMetric for LocalIntCounter {
+    fn flush(&self) {
+        let v = self.val.replace(0);
+        if v != 0 {
+            self.counter.inc_by(v);
+        }
+    }
+}
+
+impl MayFlush for LocalIntCounter {}
+
+impl Drop for LocalIntCounter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A thread-local accumulator for a [`Histogram`](::Histogram). Observations
+/// are tallied into plain per-bucket counters and a running sum, and are
+/// only folded into the shared histogram via
+/// [`flush`](LocalMetric::flush). See
+/// [`LocalCounter`](self::LocalCounter) for why this is not `Clone`.
+pub struct LocalHistogram {
+    histogram: Histogram,
+    counts: Vec<Cell<u64>>,
+    sum: Cell<f64>,
+    count: Cell<u64>,
+}
+
+impl LocalHistogram {
+    fn new(histogram: Histogram) -> LocalHistogram {
+        let n = histogram.upper_bounds().len();
+        LocalHistogram {
+            counts: vec![Cell::new(0); n],
+            sum: Cell::new(0.0),
+            count: Cell::new(0),
+            histogram,
+        }
+    }
+
+    /// Add a single observation to the local buffer.
+    pub fn observe(&self, v: f64) {
+        self.sum.set(self.sum.get() + v);
+        self.count.set(self.count.get() + 1);
+        for (i, upper_bound) in self.histogram.upper_bounds().iter().enumerate() {
+            if v <= *upper_bound {
+                self.counts[i].set(self.counts[i].get() + 1);
+            }
+        }
+    }
+}
+
+impl LocalMetric for LocalHistogram {
+    fn flush(&self) {
+        let count = self.count.replace(0);
+        if count == 0 {
+            return;
+        }
+        let sum = self.sum.replace(0.0);
+        let counts: Vec<u64> = self.counts.iter().map(|c| c.replace(0)).collect();
+        // Folds the buffered bucket counts and sum into the shared
+        // histogram's atomics in one pass, so correctness is preserved
+        // even if multiple threads flush concurrently.
+        self.histogram.flush_local(sum, count, &counts);
+    }
+}
+
+impl MayFlush for LocalHistogram {}
+
+impl Drop for LocalHistogram {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl Counter {
+    /// Return a [`LocalCounter`](self::LocalCounter) that buffers increments
+    /// for this counter on the current thread.
+    pub fn local(&self) -> LocalCounter {
+        LocalCounter::new(self.clone())
+    }
+}
+
+impl IntCounter {
+    /// Return a [`LocalIntCounter`](self::LocalIntCounter) that buffers
+    /// increments for this counter on the current thread.
+    pub fn local(&self) -> LocalIntCounter {
+        LocalIntCounter::new(self.clone())
+    }
+}
+
+impl Histogram {
+    /// Return a [`LocalHistogram`](self::LocalHistogram) that buffers
+    /// observations for this histogram on the current thread.
+    pub fn local(&self) -> LocalHistogram {
+        LocalHistogram::new(self.clone())
+    }
+}
+
+/// Build the sorted label pairs for a const metric: `desc.variable_labels`
+/// zipped with `label_values`, merged with `desc.const_label_pairs`.
+///
+/// Returns an error if `label_values` does not have exactly as many entries
+/// as `desc.variable_labels`.
+fn make_const_label_pairs(desc: &Desc, label_values: &[&str]) -> Result<Vec<LabelPair>> {
+    if desc.variable_labels.len() != label_values.len() {
+        return Err(Error::InconsistentCardinality {
+            expect: desc.variable_labels.len(),
+            got: label_values.len(),
+        });
+    }
+
+    let mut label_pairs = Vec::with_capacity(desc.variable_labels.len() + desc.const_label_pairs.len());
+    for (name, value) in desc.variable_labels.iter().zip(label_values.iter()) {
+        let mut label_pair = LabelPair::new();
+        label_pair.set_name(name.clone());
+        label_pair.set_value((*value).to_owned());
+        label_pairs.push(label_pair);
+    }
+    label_pairs.extend_from_slice(&desc.const_label_pairs);
+    label_pairs.sort();
+
+    Ok(label_pairs)
+}
+
+impl Counter {
+    /// Create a [`proto::Metric`] with a fixed `value`, without going
+    /// through a backing atomic [`Counter`](self::Counter). Intended for
+    /// [`Collector`](self::Collector) implementations that produce their
+    /// samples fresh at scrape time instead of maintaining long-lived
+    /// metric storage.
+    pub fn new_const(desc: Desc, value: f64, label_values: &[&str]) -> Result<proto::Metric> {
+        let label_pairs = make_const_label_pairs(&desc, label_values)?;
+
+        let mut counter = proto::Counter::default();
+        counter.set_value(value);
+
+        let mut m = proto::Metric::default();
+        m.set_label(label_pairs);
+        m.set_counter(counter);
+        Ok(m)
+    }
+}
+
+impl Gauge {
+    /// Create a [`proto::Metric`] with a fixed `value`. See
+    /// [`Counter::new_const`](self::Counter::new_const) for when to use
+    /// this.
+    pub fn new_const(desc: Desc, value: f64, label_values: &[&str]) -> Result<proto::Metric> {
+        let label_pairs = make_const_label_pairs(&desc, label_values)?;
+
+        let mut gauge = proto::Gauge::default();
+        gauge.set_value(value);
+
+        let mut m = proto::Metric::default();
+        m.set_label(label_pairs);
+        m.set_gauge(gauge);
+        Ok(m)
+    }
+}
+
+impl Histogram {
+    /// Create a [`proto::Metric`] from a pre-aggregated `count`, `sum`, and
+    /// cumulative per-bucket `(upper_bound, cumulative_count)` pairs. See
+    /// [`Counter::new_const`](self::Counter::new_const) for when to use
+    /// this.
+    pub fn new_const(
+        desc: Desc,
+        count: u64,
+        sum: f64,
+        buckets: Vec<(f64, u64)>,
+        label_values: &[&str],
+    ) -> Result<proto::Metric> {
+        let label_pairs = make_const_label_pairs(&desc, label_values)?;
+
+        let mut histogram = proto::Histogram::default();
+        histogram.set_sample_count(count);
+        histogram.set_sample_sum(sum);
+        histogram.set_bucket(
+            buckets
+                .into_iter()
+                .map(|(upper_bound, cumulative_count)| {
+                    let mut bucket = proto::Bucket::default();
+                    bucket.set_upper_bound(upper_bound);
+                    bucket.set_cumulative_count(cumulative_count);
+                    bucket
+                })
+                .collect(),
+        );
+
+        let mut m = proto::Metric::default();
+        m.set_label(label_pairs);
+        m.set_histogram(histogram);
+        Ok(m)
+    }
+}
+
 /// A struct that bundles the options for creating most [`Metric`](::core::Metric) types.
 #[derive(Debug, Clone)]
 pub struct Opts<L: Labels> {
@@ -389,6 +906,54 @@ fn build_fq_name(namespace: &str, subsystem: &str, name: &str) -> String {
     name.to_owned()
 }
 
+/// Prepend `prefix` to `name`, reusing the same empty-component rules as
+/// [`build_fq_name`](self::build_fq_name) (an empty prefix leaves `name`
+/// untouched). Used by [`Registry::gather`](::Registry::gather) to
+/// namespace every [`MetricFamily`](proto::MetricFamily) it returns.
+pub(crate) fn apply_prefix(prefix: &str, name: &str) -> String {
+    build_fq_name(prefix, "", name)
+}
+
+/// Merge `extra` into each metric's existing label pairs, keeping the
+/// result sorted via the [`Ord`](std::cmp::Ord) impl for
+/// [`LabelPair`](proto::LabelPair) above. Used by
+/// [`Registry::gather`](::Registry::gather) to inject its common labels
+/// into every metric it returns.
+pub(crate) fn inject_common_labels(mfs: &mut [proto::MetricFamily], extra: &[LabelPair]) {
+    if extra.is_empty() {
+        return;
+    }
+
+    for mf in mfs {
+        for m in mf.mut_metric() {
+            let mut label_pairs = m.take_label();
+            label_pairs.extend_from_slice(extra);
+            label_pairs.sort();
+            m.set_label(label_pairs);
+        }
+    }
+}
+
+pub(crate) fn common_label_pairs(labels: &HashMap<String, String>) -> Vec<LabelPair> {
+    let mut label_pairs: Vec<LabelPair> = labels
+        .iter()
+        .map(|(name, value)| {
+            let mut label_pair = LabelPair::new();
+            label_pair.set_name(name.clone());
+            label_pair.set_value(value.clone());
+            label_pair
+        })
+        .collect();
+    label_pairs.sort();
+    label_pairs
+}
+
+// `Registry`'s prefix/common-label API (`new_with_prefix_and_labels`,
+// `with_prefix`, `with_labels`, `sub_registry_with_prefix`,
+// `sub_registry_with_labels`) lives in `registry.rs`, next to the rest of
+// `Registry`'s storage and its `gather()` method, which is what actually
+// calls `apply_prefix`/`inject_common_labels` above.
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -434,4 +999,148 @@ mod tests {
             assert_eq!(&build_fq_name(namespace, subsystem, name), res);
         }
     }
+
+    struct CountingFlush {
+        flushed: Cell<u32>,
+    }
+
+    impl LocalMetric for CountingFlush {
+        fn flush(&self) {
+            self.flushed.set(self.flushed.get() + 1);
+        }
+    }
+
+    impl MayFlush for CountingFlush {}
+
+    #[test]
+    fn test_may_flush_respects_interval() {
+        let m = CountingFlush {
+            flushed: Cell::new(0),
+        };
+        let last_flush = Cell::new(0);
+
+        // `last_flush` starts at 0, so "now" (elapsed millis since the
+        // clock's first use) is always >= it: the first call always flushes.
+        m.try_flush(&last_flush, 0);
+        assert_eq!(m.flushed.get(), 1);
+        let just_flushed = last_flush.get();
+
+        // Immediately trying again with a huge interval must not flush,
+        // and must leave `last_flush` untouched.
+        m.try_flush(&last_flush, u64::MAX);
+        assert_eq!(m.flushed.get(), 1);
+        assert_eq!(last_flush.get(), just_flushed);
+    }
+
+    #[test]
+    fn test_make_const_label_pairs_checks_cardinality() {
+        let desc = Desc::new(
+            "test_metric".to_owned(),
+            "help".to_owned(),
+            vec!["a".to_owned(), "b".to_owned()],
+            HashMap::new(),
+        )
+        .unwrap();
+
+        match make_const_label_pairs(&desc, &["1"]) {
+            Err(Error::InconsistentCardinality { expect, got }) => {
+                assert_eq!(expect, 2);
+                assert_eq!(got, 1);
+            }
+            other => panic!("expected InconsistentCardinality, got {:?}", other),
+        }
+
+        let label_pairs = make_const_label_pairs(&desc, &["1", "2"]).unwrap();
+        let names: Vec<&str> = label_pairs.iter().map(|l| l.get_name()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_apply_prefix_and_inject_common_labels() {
+        assert_eq!(apply_prefix("", "requests_total"), "requests_total");
+        assert_eq!(
+            apply_prefix("myapp", "requests_total"),
+            "myapp_requests_total"
+        );
+
+        let mut mf = proto::MetricFamily::default();
+        let mut m = proto::Metric::default();
+        m.set_label(vec![new_label_pair("path", "/")]);
+        mf.set_metric(vec![m]);
+
+        let extra = vec![new_label_pair("service", "api")];
+        inject_common_labels(&mut [mf.clone()], &[]);
+        assert_eq!(mf.get_metric()[0].get_label().len(), 1);
+
+        let mut mfs = [mf];
+        inject_common_labels(&mut mfs, &extra);
+        let labels = mfs[0].get_metric()[0].get_label();
+        assert_eq!(labels.len(), 2);
+        // `inject_common_labels` keeps the merged label pairs sorted by name.
+        assert_eq!(labels[0].get_name(), "path");
+        assert_eq!(labels[1].get_name(), "service");
+    }
+
+    struct RecordingEncoder {
+        counter_value: Option<f64>,
+        labels: Vec<LabelPair>,
+    }
+
+    impl MetricEncoder for RecordingEncoder {
+        fn encode_counter(&mut self, value: f64) -> Result<()> {
+            self.counter_value = Some(value);
+            Ok(())
+        }
+
+        fn encode_gauge(&mut self, _value: f64) -> Result<()> {
+            Ok(())
+        }
+
+        fn encode_histogram(&mut self, _buckets: &[(f64, u64)], _sum: f64, _count: u64) -> Result<()> {
+            Ok(())
+        }
+
+        fn encode_labels(&mut self, labels: &[LabelPair]) -> Result<()> {
+            self.labels = labels.to_vec();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_encode_metric_drives_encoder() {
+        let counter = Counter::new("test_requests_total", "help").unwrap();
+        counter.inc_by(3.0);
+
+        let mut encoder = RecordingEncoder {
+            counter_value: None,
+            labels: vec![],
+        };
+        EncodeMetric::encode(&counter, &mut encoder).unwrap();
+
+        assert_eq!(encoder.counter_value, Some(3.0));
+    }
+
+    #[test]
+    fn test_exemplar_rejects_labels_over_rune_limit() {
+        let mut labels = HashMap::new();
+        labels.insert("trace_id".to_owned(), "a".repeat(200));
+
+        assert!(Exemplar::new(labels, 1.0, &[]).is_err());
+
+        let mut small_labels = HashMap::new();
+        small_labels.insert("trace_id".to_owned(), "abc123".to_owned());
+        let exemplar = Exemplar::new(small_labels, 1.0, &[]).unwrap();
+        assert_eq!(exemplar.value(), 1.0);
+    }
+
+    #[test]
+    fn test_inc_with_exemplar_rejects_before_mutating() {
+        let counter = Counter::new("test_requests_total", "help").unwrap();
+        let mut labels = HashMap::new();
+        labels.insert("trace_id".to_owned(), "a".repeat(200));
+
+        assert!(counter.inc_with_exemplar(labels).is_err());
+        assert_eq!(counter.get(), 0.0);
+        assert!(counter.exemplar().is_none());
+    }
 }
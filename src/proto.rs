@@ -0,0 +1,225 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hand-written stand-ins for the wire types a real build would generate
+//! from `metrics.proto`. Shaped like the generated code (plain structs with
+//! `get_*`/`set_*` accessors) so the rest of the crate reads the same way
+//! it would against the generated types.
+
+/// A single `name`/`value` label.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelPair {
+    name: String,
+    value: String,
+}
+
+impl LabelPair {
+    pub fn new() -> LabelPair {
+        LabelPair::default()
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: String) {
+        self.value = value;
+    }
+}
+
+/// A counter's wire representation: a single monotonically increasing
+/// value.
+#[derive(Debug, Clone, Default)]
+pub struct Counter {
+    value: f64,
+}
+
+impl Counter {
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: f64) {
+        self.value = value;
+    }
+}
+
+/// A gauge's wire representation: a single value that can go up or down.
+#[derive(Debug, Clone, Default)]
+pub struct Gauge {
+    value: f64,
+}
+
+impl Gauge {
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn set_value(&mut self, value: f64) {
+        self.value = value;
+    }
+}
+
+/// One cumulative histogram bucket.
+#[derive(Debug, Clone, Default)]
+pub struct Bucket {
+    upper_bound: f64,
+    cumulative_count: u64,
+}
+
+impl Bucket {
+    pub fn get_upper_bound(&self) -> f64 {
+        self.upper_bound
+    }
+
+    pub fn set_upper_bound(&mut self, upper_bound: f64) {
+        self.upper_bound = upper_bound;
+    }
+
+    pub fn get_cumulative_count(&self) -> u64 {
+        self.cumulative_count
+    }
+
+    pub fn set_cumulative_count(&mut self, cumulative_count: u64) {
+        self.cumulative_count = cumulative_count;
+    }
+}
+
+/// A histogram's wire representation: a running sample count and sum,
+/// plus its cumulative buckets.
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    sample_count: u64,
+    sample_sum: f64,
+    bucket: Vec<Bucket>,
+}
+
+impl Histogram {
+    pub fn get_sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: u64) {
+        self.sample_count = sample_count;
+    }
+
+    pub fn get_sample_sum(&self) -> f64 {
+        self.sample_sum
+    }
+
+    pub fn set_sample_sum(&mut self, sample_sum: f64) {
+        self.sample_sum = sample_sum;
+    }
+
+    pub fn get_bucket(&self) -> &[Bucket] {
+        &self.bucket
+    }
+
+    pub fn set_bucket(&mut self, bucket: Vec<Bucket>) {
+        self.bucket = bucket;
+    }
+}
+
+/// A single sample: its labels plus exactly one of counter/gauge/histogram.
+#[derive(Debug, Clone, Default)]
+pub struct Metric {
+    label: Vec<LabelPair>,
+    counter: Option<Counter>,
+    gauge: Option<Gauge>,
+    histogram: Option<Histogram>,
+}
+
+impl Metric {
+    pub fn get_label(&self) -> &[LabelPair] {
+        &self.label
+    }
+
+    pub fn set_label(&mut self, label: Vec<LabelPair>) {
+        self.label = label;
+    }
+
+    pub fn take_label(&mut self) -> Vec<LabelPair> {
+        ::std::mem::take(&mut self.label)
+    }
+
+    pub fn get_counter(&self) -> &Counter {
+        self.counter.as_ref().expect("metric has no counter")
+    }
+
+    pub fn set_counter(&mut self, counter: Counter) {
+        self.counter = Some(counter);
+    }
+
+    pub fn get_gauge(&self) -> &Gauge {
+        self.gauge.as_ref().expect("metric has no gauge")
+    }
+
+    pub fn set_gauge(&mut self, gauge: Gauge) {
+        self.gauge = Some(gauge);
+    }
+
+    pub fn get_histogram(&self) -> &Histogram {
+        self.histogram.as_ref().expect("metric has no histogram")
+    }
+
+    pub fn set_histogram(&mut self, histogram: Histogram) {
+        self.histogram = Some(histogram);
+    }
+}
+
+/// A named group of [`Metric`] samples sharing the same help text.
+#[derive(Debug, Clone, Default)]
+pub struct MetricFamily {
+    name: String,
+    help: String,
+    metric: Vec<Metric>,
+}
+
+impl MetricFamily {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn get_help(&self) -> &str {
+        &self.help
+    }
+
+    pub fn set_help(&mut self, help: String) {
+        self.help = help;
+    }
+
+    pub fn get_metric(&self) -> &[Metric] {
+        &self.metric
+    }
+
+    pub fn set_metric(&mut self, metric: Vec<Metric>) {
+        self.metric = metric;
+    }
+
+    pub fn mut_metric(&mut self) -> &mut Vec<Metric> {
+        &mut self.metric
+    }
+}
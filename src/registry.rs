@@ -0,0 +1,269 @@
+// Copyright 2014 The Prometheus Authors
+// Copyright 2016 PingCAP, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use errors::{Error, Result};
+use metrics::{apply_prefix, common_label_pairs, inject_common_labels, Collector};
+use proto::{LabelPair, MetricFamily};
+
+struct RegistryCore {
+    collectors_by_id: HashMap<u64, Box<dyn Collector>>,
+    desc_ids: HashSet<u64>,
+    dim_hashes_by_name: HashMap<String, u64>,
+    prefix: String,
+    common_label_pairs: Vec<LabelPair>,
+    // Sub-registries created via `sub_registry_with_*`; their metrics are
+    // folded into `self`'s `gather()` output.
+    children: Vec<Registry>,
+}
+
+/// `Registry` collects [`Collector`](::Collector)s and gathers their
+/// metrics into a flat list of [`MetricFamily`](::proto::MetricFamily) on
+/// demand.
+#[derive(Clone)]
+pub struct Registry {
+    r: Arc<RwLock<RegistryCore>>,
+}
+
+impl Registry {
+    /// Create an empty `Registry`.
+    pub fn new() -> Registry {
+        Registry {
+            r: Arc::new(RwLock::new(RegistryCore {
+                collectors_by_id: HashMap::new(),
+                desc_ids: HashSet::new(),
+                dim_hashes_by_name: HashMap::new(),
+                prefix: String::new(),
+                common_label_pairs: Vec::new(),
+                children: Vec::new(),
+            })),
+        }
+    }
+
+    /// Creates a `Registry` that namespaces every metric gathered through
+    /// it: `prefix` (if any) is prepended to each
+    /// [`MetricFamily`](::proto::MetricFamily) name, and `labels` (if any)
+    /// are injected as common labels on every gathered metric.
+    pub fn new_with_prefix_and_labels(
+        prefix: Option<String>,
+        labels: Option<HashMap<String, String>>,
+    ) -> Registry {
+        Registry::new()
+            .with_prefix(prefix.unwrap_or_default())
+            .with_labels(labels.unwrap_or_default())
+    }
+
+    /// Set (or replace) the prefix prepended to every metric name gathered
+    /// through this registry.
+    pub fn with_prefix<S: Into<String>>(self, prefix: S) -> Registry {
+        self.r.write().unwrap().prefix = prefix.into();
+        self
+    }
+
+    /// Set (or replace) the common labels injected into every metric
+    /// gathered through this registry.
+    pub fn with_labels(self, labels: HashMap<String, String>) -> Registry {
+        self.r.write().unwrap().common_label_pairs = common_label_pairs(&labels);
+        self
+    }
+
+    /// This registry's current prefix (empty if none was set).
+    pub fn prefix(&self) -> String {
+        self.r.read().unwrap().prefix.clone()
+    }
+
+    /// This registry's current common labels (empty if none were set).
+    ///
+    /// Returns an owned `HashMap`, not the `Vec<LabelPair>` `self` stores
+    /// internally, so callers such as
+    /// [`sub_registry_with_labels`](Registry::sub_registry_with_labels)
+    /// can merge it directly with a caller-supplied `HashMap`.
+    pub fn common_labels(&self) -> HashMap<String, String> {
+        self.r
+            .read()
+            .unwrap()
+            .common_label_pairs
+            .iter()
+            .map(|lp| (lp.get_name().to_owned(), lp.get_value().to_owned()))
+            .collect()
+    }
+
+    fn new_child(&self) -> Registry {
+        let child = Registry::new();
+        self.r.write().unwrap().children.push(child.clone());
+        child
+    }
+
+    /// Return a child registry whose prefix is `self`'s prefix joined with
+    /// `prefix`. Collectors registered on the child are still gathered
+    /// when `self` is gathered.
+    pub fn sub_registry_with_prefix<S: Into<String>>(&self, prefix: S) -> Registry {
+        let parent_prefix = self.prefix();
+        let prefix = prefix.into();
+        // `apply_prefix` treats an empty second argument as an empty
+        // *metric name* and short-circuits to "" -- correct for gather(),
+        // wrong here where an empty `prefix` means "inherit the parent's
+        // prefix unchanged", not "produce no prefix at all".
+        let joined = if prefix.is_empty() {
+            parent_prefix
+        } else {
+            apply_prefix(&parent_prefix, &prefix)
+        };
+        let labels = self.common_labels();
+        let child = self.new_child();
+        child.with_prefix(joined).with_labels(labels)
+    }
+
+    /// Return a child registry that additionally injects `labels` on top
+    /// of `self`'s common labels. Collectors registered on the child are
+    /// still gathered when `self` is gathered.
+    pub fn sub_registry_with_labels(&self, labels: HashMap<String, String>) -> Registry {
+        let mut merged = self.common_labels();
+        merged.extend(labels);
+        let prefix = self.prefix();
+        let child = self.new_child();
+        child.with_labels(merged).with_prefix(prefix)
+    }
+
+    /// Register a collector. Fails if any of its descriptors collide with
+    /// one already registered, or if a metric of the same name was
+    /// previously registered with a different set of variable labels.
+    pub fn try_register(&self, c: Box<dyn Collector>) -> Result<()> {
+        let mut core = self.r.write().unwrap();
+        let descs = c.desc();
+
+        for d in &descs {
+            if core.desc_ids.contains(&d.id) {
+                return Err(Error::AlreadyReg);
+            }
+        }
+
+        for d in &descs {
+            if let Some(dim_hash) = core.dim_hashes_by_name.get(&d.fq_name) {
+                if *dim_hash != d.dim_hash {
+                    return Err(Error::Msg(format!(
+                        "a metric named {} was already registered with a different set of \
+                         variable labels",
+                        d.fq_name
+                    )));
+                }
+            }
+        }
+
+        let id = descs.iter().fold(0u64, |acc, d| acc ^ d.id);
+        for d in &descs {
+            core.desc_ids.insert(d.id);
+            core.dim_hashes_by_name
+                .insert(d.fq_name.clone(), d.dim_hash);
+        }
+        core.collectors_by_id.insert(id, c);
+        Ok(())
+    }
+
+    /// Unregister a previously registered collector.
+    pub fn try_unregister(&self, c: Box<dyn Collector>) -> Result<()> {
+        let mut core = self.r.write().unwrap();
+        let descs = c.desc();
+        let id = descs.iter().fold(0u64, |acc, d| acc ^ d.id);
+
+        if core.collectors_by_id.remove(&id).is_none() {
+            return Err(Error::Msg("collector is not registered".to_owned()));
+        }
+        for d in &descs {
+            core.desc_ids.remove(&d.id);
+        }
+        Ok(())
+    }
+
+    /// Gather metrics from every registered collector and every
+    /// sub-registry, rewriting names with this registry's prefix and
+    /// injecting its common labels.
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        let core = self.r.read().unwrap();
+
+        let mut mfs: Vec<MetricFamily> = core
+            .collectors_by_id
+            .values()
+            .flat_map(|c| c.collect())
+            .collect();
+
+        if !core.prefix.is_empty() {
+            for mf in &mut mfs {
+                let name = apply_prefix(&core.prefix, mf.get_name());
+                mf.set_name(name);
+            }
+        }
+        inject_common_labels(&mut mfs, &core.common_label_pairs);
+
+        for child in &core.children {
+            mfs.extend(child.gather());
+        }
+        mfs
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_registry_with_prefix_inherits_parent_prefix() {
+        let parent = Registry::new().with_prefix("parent");
+
+        // An empty child prefix must inherit the parent's prefix
+        // unchanged, not collapse to "" the way `apply_prefix("parent",
+        // "")` would if used directly.
+        let inherited = parent.sub_registry_with_prefix("");
+        assert_eq!(inherited.prefix(), "parent");
+
+        let joined = parent.sub_registry_with_prefix("child");
+        assert_eq!(joined.prefix(), "parent_child");
+
+        let no_parent_prefix = Registry::new().sub_registry_with_prefix("child");
+        assert_eq!(no_parent_prefix.prefix(), "child");
+    }
+
+    #[test]
+    fn test_sub_registry_with_labels_merges_parent_labels() {
+        let mut parent_labels = HashMap::new();
+        parent_labels.insert("service".to_owned(), "api".to_owned());
+        let parent = Registry::new().with_labels(parent_labels);
+
+        let mut child_labels = HashMap::new();
+        child_labels.insert("shard".to_owned(), "1".to_owned());
+        let child = parent.sub_registry_with_labels(child_labels);
+
+        let labels = child.common_labels();
+        assert_eq!(labels.get("service").map(String::as_str), Some("api"));
+        assert_eq!(labels.get("shard").map(String::as_str), Some("1"));
+
+        // A label the child sets must override the parent's, not just be
+        // merged alongside it.
+        let mut overriding = HashMap::new();
+        overriding.insert("service".to_owned(), "worker".to_owned());
+        let overridden_child = parent.sub_registry_with_labels(overriding);
+        assert_eq!(
+            overridden_child.common_labels().get("service").map(String::as_str),
+            Some("worker")
+        );
+    }
+}